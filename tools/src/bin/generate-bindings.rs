@@ -4,20 +4,27 @@
 
 extern crate bindgen;
 
+mod host;
+mod overrides;
+mod toolchain;
+
 use std::{env, fs};
 use std::path::{PathBuf, Path};
 
 use clap::{self, value_parser, crate_authors, crate_description, crate_version};
-use serde::Deserialize;
+
+use tools::native_build::NativeBuild;
+use tools::sdk_opts::load_sdk_opts;
 
 const OUTFILE: &str = "bindings.rs";
 const SDK_OPTS: &str = "sdk.opts";
-#[cfg(windows)]
-const TOOLCHAIN: &str = "../../../toolchain/i686-windows/arm-none-eabi/include";
-#[cfg(linux)]
-const TOOLCHAIN: &str = "../../../toolchain/x86_64-linux/arm-none-eabi/include";
 const VISIBILITY_PUBLIC: &str = "+";
 
+/// Build the path to the bundled toolchain's `include` directory for `triple`.
+fn toolchain_path(triple: &str) -> PathBuf {
+    PathBuf::from(format!("../../../toolchain/{}/arm-none-eabi/include", triple))
+}
+
 #[derive(Debug)]
 struct ApiSymbols {
     pub api_version: u32,
@@ -73,27 +80,6 @@ fn load_symbols<T: AsRef<Path>>(path: T) -> ApiSymbols {
     ApiSymbols { api_version, headers, functions, variables }
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
-struct SdkOpts {
-    sdk_symbols: String,
-    cc_args: String,
-    cpp_args: String,
-    linker_args: String,
-    linker_script: String,
-}
-
-/// Load `sdk.opts` file of compiler flags.
-fn load_sdk_opts<T: AsRef<Path>>(path: T) -> SdkOpts {
-    let file = fs::File::open(path.as_ref())
-        .expect("failed to open sdk.opts");
-
-    let sdk_opts: SdkOpts = serde_json::from_reader(file)
-        .expect("failed to parse sdk.opts JSON");
-
-    sdk_opts
-}
-
 /// Generate bindings header.
 fn generate_bindings_header(api_symbols: &ApiSymbols) -> String {
     let mut lines = Vec::new();
@@ -117,6 +103,67 @@ fn parse_args() -> clap::ArgMatches {
             clap::Arg::new("sdk")
                 .value_parser(value_parser!(PathBuf))
         )
+        .arg(
+            clap::Arg::new("offline")
+                .long("offline")
+                .action(clap::ArgAction::SetTrue)
+                .help("fail instead of downloading a missing toolchain")
+        )
+        .arg(
+            clap::Arg::new("host-triple")
+                .long("host-triple")
+                .help("override the detected host (e.g. x86_64-linux, aarch64-darwin)")
+        )
+        .arg(
+            clap::Arg::new("native-src")
+                .long("native-src")
+                .value_parser(value_parser!(PathBuf))
+                .action(clap::ArgAction::Append)
+                .help("bundle a .c/.cpp/.s file into a static archive linked against the SDK")
+        )
+        .arg(
+            clap::Arg::new("native-out")
+                .long("native-out")
+                .default_value("flipperzero-native")
+                .help("name (without lib/.a) of the static archive built from --native-src files")
+        )
+        .arg(
+            clap::Arg::new("native-out-dir")
+                .long("native-out-dir")
+                .value_parser(value_parser!(PathBuf))
+                .help(
+                    "directory for --native-src object files and the archive \
+                     (default: ./native next to bindings.rs); created if missing. \
+                     This is a standalone CLI, not a build.rs, so TARGET/HOST/OPT_LEVEL/OUT_DIR \
+                     aren't read from cargo's environment — pass this instead of OUT_DIR"
+                )
+        )
+        .arg(
+            clap::Arg::new("out")
+                .long("out")
+                .value_parser(value_parser!(PathBuf))
+                .help("where to write bindings.rs (default: ./bindings.rs, or $FLIPPERZERO_OUT)")
+        )
+        .arg(
+            clap::Arg::new("clang-arg")
+                .long("clang-arg")
+                .action(clap::ArgAction::Append)
+                .help("extra flag appended after the SDK's own clang args (repeatable)")
+        )
+        .arg(
+            clap::Arg::new("define")
+                .long("define")
+                .value_name("NAME=VALUE")
+                .action(clap::ArgAction::Append)
+                .help("extra preprocessor define appended after the SDK's own (repeatable)")
+        )
+        .arg(
+            clap::Arg::new("include-dir")
+                .long("include-dir")
+                .value_parser(value_parser!(PathBuf))
+                .action(clap::ArgAction::Append)
+                .help("extra include directory appended after the SDK's own (repeatable)")
+        )
         .get_matches()
 }
 
@@ -135,17 +182,6 @@ fn main() {
     let cwd = env::current_dir().unwrap();
     let sdk = cwd.join(&sdk);
 
-    let toolchain = sdk.join(TOOLCHAIN);
-    if !toolchain.is_dir() {
-        panic!(
-            concat!(
-                "Failed to find toolchain at {:?}.\n",
-                "You may need to download it first."
-            ),
-            TOOLCHAIN
-        )
-    }
-
     let replace_sdk_root_dir = |s: &str| {
         // Need to use '/' on Windows, or else include paths don't work
         s.replace("SDK_ROOT_DIR", sdk.to_str().unwrap()).replace("\\", "/")
@@ -158,10 +194,24 @@ fn main() {
     let symbols = load_symbols(&sdk.join(&replace_sdk_root_dir(&sdk_opts.sdk_symbols)));
     let bindings_header = generate_bindings_header(&symbols);
 
+    // Fetch and cache the toolchain if it isn't already bundled with the SDK
+    let offline = matches.get_flag("offline");
+    let host_triple = host::host_triple(matches.get_one::<String>("host-triple").map(String::as_str));
+    let toolchain = toolchain::ensure_toolchain(
+        &sdk.join(toolchain_path(&host_triple)),
+        symbols.api_version,
+        &host_triple,
+        offline,
+    );
+
     // Some of the values are shell-quoted
     let cc_flags = shlex::split(&replace_sdk_root_dir(&sdk_opts.cc_args))
         .expect("failed to split sdk.opts cc_args");
 
+    // FLIPPERZERO_* env vars and --clang-arg/--define/--include-dir: merged
+    // in on top of (i.e. after) the SDK's own flags, never in place of them.
+    let override_args = overrides::clang_args(&matches);
+
     // Generate bindings
     eprintln!("Generating bindings for SDK {:08X}", symbols.api_version);
     let mut bindings = bindgen::builder()
@@ -172,6 +222,7 @@ fn main() {
         .clang_args(&cc_flags)
         .clang_arg("-Wno-error")
         .clang_arg("-fshort-enums")
+        .clang_args(&override_args)
         .use_core()
         .ctypes_prefix("core::ffi")
         .allowlist_var("API_VERSION")
@@ -188,9 +239,35 @@ fn main() {
     let bindings = bindings.generate().expect("failed to generate bindings");
 
     // `-working-directory` also affects `Bindings::write_to_file`
-    let outfile = cwd.join(OUTFILE);
+    let outfile = overrides::out_path(&matches, cwd.join(OUTFILE));
+    let outfile = if outfile.is_absolute() { outfile } else { cwd.join(outfile) };
 
-    eprintln!("Writing to {:?}", OUTFILE);
-    bindings.write_to_file(outfile)
+    eprintln!("Writing to {:?}", outfile);
+    bindings.write_to_file(&outfile)
         .expect("failed to write bindings");
+
+    // Compile any bundled native sources against the same SDK flags
+    if let Some(native_srcs) = matches.get_many::<PathBuf>("native-src") {
+        let toolchain_bin = toolchain
+            .parent()
+            .and_then(Path::parent)
+            .expect("unexpected toolchain include path")
+            .join("bin");
+
+        let native_out = matches.get_one::<String>("native-out").unwrap();
+
+        // This binary isn't a build.rs, so there's no cargo-provided OUT_DIR
+        let native_out_dir = matches
+            .get_one::<PathBuf>("native-out-dir")
+            .cloned()
+            .unwrap_or_else(|| outfile.parent().unwrap().join("native"));
+        fs::create_dir_all(&native_out_dir)
+            .unwrap_or_else(|e| panic!("failed to create {:?}: {}", native_out_dir, e));
+
+        eprintln!("Compiling native sources into lib{}.a", native_out);
+        let mut native_build = NativeBuild::new(&sdk_opts, &sdk, &toolchain_bin, &native_out_dir);
+        native_build.files(native_srcs);
+        overrides::apply_to_native_build(&mut native_build, &matches);
+        native_build.compile(native_out);
+    }
 }
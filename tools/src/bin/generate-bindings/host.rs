@@ -0,0 +1,55 @@
+//! Detect the host triple used to locate the bundled toolchain.
+//!
+//! Unsupported combinations get a clear error (or an explicit override)
+//! instead of silently picking the wrong toolchain.
+
+/// Directory name, relative to `toolchain/`, that bundles the
+/// `arm-none-eabi` toolchain for a given host.
+///
+/// Recognizes `FLIPPERZERO_HOST_TRIPLE` and `--host-triple` as an explicit
+/// override for hosts this can't detect (or to force a different bundled
+/// toolchain than the one that matches `std::env::consts`).
+pub fn host_triple(override_triple: Option<&str>) -> String {
+    if let Some(triple) = override_triple {
+        return triple.to_string();
+    }
+
+    if let Ok(triple) = std::env::var("FLIPPERZERO_HOST_TRIPLE") {
+        return triple;
+    }
+
+    detect_host_triple().unwrap_or_else(|| {
+        panic!(
+            "unable to determine toolchain directory for host OS {:?} / arch {:?}.\n\
+             Pass --host-triple or set FLIPPERZERO_HOST_TRIPLE to override.",
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+        )
+    })
+}
+
+/// Map `std::env::consts::{OS, ARCH}` to a bundled toolchain directory name.
+fn detect_host_triple() -> Option<String> {
+    let arch = match std::env::consts::ARCH {
+        "x86" => "i686",
+        "x86_64" => "x86_64",
+        "aarch64" => "aarch64",
+        _ => return None,
+    };
+
+    let os = match std::env::consts::OS {
+        "windows" => "windows",
+        "linux" => "linux",
+        "macos" => "darwin",
+        _ => return None,
+    };
+
+    // Upstream only publishes 32-bit Windows and 64-bit Linux/macOS toolchains.
+    let arch = match (arch, os) {
+        ("i686" | "x86_64", "windows") => "i686",
+        ("x86_64" | "aarch64", "linux" | "darwin") => arch,
+        _ => return None,
+    };
+
+    Some(format!("{}-{}", arch, os))
+}
@@ -0,0 +1,120 @@
+//! User-supplied overrides for compiler/bindgen flags and output path.
+//!
+//! Overrides are *appended* on top of the SDK-derived flags rather than
+//! replacing them, so e.g. the `-fshort-enums` that `generate-bindings`
+//! passes after the SDK flags can still be countermanded by a user's own
+//! `--clang-arg`. Both `FLIPPERZERO_*`
+//! environment variables and their matching CLI flags are honored; CLI
+//! flags are appended after the environment so they win when both are given.
+
+use std::path::PathBuf;
+
+use tools::native_build::NativeBuild;
+
+/// Extra clang/bindgen arguments from `FLIPPERZERO_CLANG_ARGS`,
+/// `FLIPPERZERO_DEFINE`, and `FLIPPERZERO_INCLUDE_DIR`, plus `--clang-arg`,
+/// `--define`, and `--include-dir` on the command line.
+///
+/// Returned in the order they must be passed to `clang_args`: environment
+/// first, then CLI, so CLI flags are the final (and therefore winning) word.
+pub fn clang_args(matches: &clap::ArgMatches) -> Vec<String> {
+    let mut args = Vec::new();
+
+    if let Ok(raw) = std::env::var("FLIPPERZERO_CLANG_ARGS") {
+        args.extend(shlex::split(&raw).expect("failed to split FLIPPERZERO_CLANG_ARGS"));
+    }
+    for entry in env_list("FLIPPERZERO_DEFINE") {
+        args.push(define_flag(&entry));
+    }
+    for dir in env_list("FLIPPERZERO_INCLUDE_DIR") {
+        args.push(format!("-I{}", dir));
+    }
+
+    if let Some(values) = matches.get_many::<String>("clang-arg") {
+        args.extend(values.cloned());
+    }
+    if let Some(values) = matches.get_many::<String>("define") {
+        args.extend(values.map(|entry| define_flag(entry)));
+    }
+    if let Some(values) = matches.get_many::<PathBuf>("include-dir") {
+        for dir in values {
+            args.push(format!("-I{}", dir.display()));
+        }
+    }
+
+    args
+}
+
+/// Split a `NAME[=VALUE]` entry into its name and value. A bare `NAME` is
+/// *not* the same as `NAME=` (an empty-string value) — it means "defined
+/// with no value", so the value side is `None` rather than `Some("")`.
+fn parse_define(entry: &str) -> (&str, Option<&str>) {
+    match entry.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (entry, None),
+    }
+}
+
+/// Render a `NAME[=VALUE]` entry as clang would: `-DNAME` when there's no
+/// value, `-DNAME=VALUE` otherwise.
+fn define_flag(entry: &str) -> String {
+    match parse_define(entry) {
+        (name, Some(value)) => format!("-D{}={}", name, value),
+        (name, None) => format!("-D{}", name),
+    }
+}
+
+/// Apply the same `--clang-arg`/`--define`/`--include-dir` overrides (and
+/// their `FLIPPERZERO_*` env equivalents) used for bindgen to a native
+/// source build, so a shim compiled via `--native-src` sees the exact same
+/// defines the generated bindings were parsed with.
+pub fn apply_to_native_build(build: &mut NativeBuild, matches: &clap::ArgMatches) {
+    if let Ok(raw) = std::env::var("FLIPPERZERO_CLANG_ARGS") {
+        for arg in shlex::split(&raw).expect("failed to split FLIPPERZERO_CLANG_ARGS") {
+            build.flag(&arg);
+        }
+    }
+    for entry in env_list("FLIPPERZERO_DEFINE") {
+        let (name, value) = parse_define(&entry);
+        build.define(name, value);
+    }
+    for dir in env_list("FLIPPERZERO_INCLUDE_DIR") {
+        build.include(dir);
+    }
+
+    if let Some(values) = matches.get_many::<String>("clang-arg") {
+        for arg in values {
+            build.flag(arg);
+        }
+    }
+    if let Some(values) = matches.get_many::<String>("define") {
+        for entry in values {
+            let (name, value) = parse_define(entry);
+            build.define(name, value);
+        }
+    }
+    if let Some(values) = matches.get_many::<PathBuf>("include-dir") {
+        for dir in values {
+            build.include(dir);
+        }
+    }
+}
+
+/// Output path for `bindings.rs`: `--out`, else `FLIPPERZERO_OUT`, else the
+/// caller's default.
+pub fn out_path(matches: &clap::ArgMatches, default: PathBuf) -> PathBuf {
+    if let Some(path) = matches.get_one::<PathBuf>("out") {
+        return path.clone();
+    }
+    if let Ok(path) = std::env::var("FLIPPERZERO_OUT") {
+        return PathBuf::from(path);
+    }
+    default
+}
+
+/// Split a comma-separated environment variable into its entries.
+fn env_list(var: &str) -> Vec<String> {
+    std::env::var(var)
+        .map(|raw| raw.split(',').map(str::to_string).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,180 @@
+//! Fetch and cache the `arm-none-eabi` toolchain used to parse SDK headers.
+//!
+//! When the toolchain isn't already present next to the SDK, download the
+//! archive for the host, verify it against the published checksum, and
+//! unpack it into a per-version, per-host cache directory so repeated runs
+//! (and other projects on the same machine) can reuse it.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// One published `arm-none-eabi` toolchain release.
+struct ToolchainRelease {
+    /// SDK `api_version` (as packed by `load_symbols`) that this toolchain
+    /// revision was verified against.
+    api_version: u32,
+    /// Download URL template with a `{host}` placeholder for the host
+    /// triple (see `host::host_triple`), since the archive's
+    /// `arm-none-eabi-gcc`/`-g++`/`-ar` binaries are host-specific.
+    url_template: &'static str,
+}
+
+/// Known-good toolchain releases, newest first.
+///
+/// The SDK's `api_version` (from `api_symbols.csv`) picks the entry here, so
+/// the headers bindgen parses and the toolchain that ships with them never
+/// drift apart.
+///
+/// There's deliberately no hardcoded checksum here: a baked-in digest would
+/// need a source change (and a new release of this tool) every time upstream
+/// cuts a toolchain build. Instead `download_and_verify` fetches the
+/// published `<archive>.sha256` alongside the archive itself and verifies
+/// against that.
+const RELEASES: &[ToolchainRelease] = &[
+    ToolchainRelease {
+        api_version: 0x0001_0000,
+        url_template: "https://update.flipperzero.one/builds/toolchain/{host}/arm-none-eabi.tar.gz",
+    },
+];
+
+/// Find the toolchain release matching `api_version`, falling back to the
+/// newest known release with a warning.
+fn select_release(api_version: u32) -> &'static ToolchainRelease {
+    RELEASES
+        .iter()
+        .find(|r| r.api_version == api_version)
+        .unwrap_or_else(|| {
+            eprintln!(
+                "warning: no toolchain release pinned for API version 0x{:08X}, \
+                 using the newest known release",
+                api_version
+            );
+            &RELEASES[0]
+        })
+}
+
+/// Directory used to cache unpacked toolchains for `host_triple`, honoring
+/// `XDG_CACHE_HOME`.
+fn cache_dir(host_triple: &str) -> PathBuf {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let home = std::env::var_os("HOME").expect("HOME is not set");
+            PathBuf::from(home).join(".cache")
+        });
+
+    base.join("flipperzero-toolchain").join(host_triple)
+}
+
+/// Fetch `url` as a UTF-8 string.
+fn fetch_text(url: &str) -> String {
+    let response = ureq::get(url)
+        .call()
+        .unwrap_or_else(|e| panic!("failed to download {}: {}", url, e));
+
+    response
+        .into_string()
+        .unwrap_or_else(|e| panic!("{} did not return UTF-8 text: {}", url, e))
+}
+
+/// Download `url`, verify it against the checksum published at
+/// `<url>.sha256` (a standard `sha256sum`-style `<digest>  <filename>` line),
+/// and return the archive bytes.
+fn download_and_verify(url: &str) -> Vec<u8> {
+    let checksum_url = format!("{}.sha256", url);
+    eprintln!("Fetching checksum from {}", checksum_url);
+    let checksum_line = fetch_text(&checksum_url);
+    let expected_sha256 = checksum_line
+        .split_whitespace()
+        .next()
+        .unwrap_or_else(|| panic!("{} is empty", checksum_url));
+
+    eprintln!("Downloading toolchain from {}", url);
+    let response = ureq::get(url)
+        .call()
+        .unwrap_or_else(|e| panic!("failed to download toolchain from {}: {}", url, e));
+
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut body)
+        .expect("failed to read toolchain archive");
+
+    let mut hasher = Sha256::new();
+    hasher.update(&body);
+    let digest = hex_encode(&hasher.finalize());
+
+    if digest != expected_sha256 {
+        panic!(
+            "toolchain archive checksum mismatch for {}: expected {}, got {}",
+            url, expected_sha256, digest
+        );
+    }
+
+    body
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Unpack a `.tar.gz` toolchain archive into `dest`.
+fn unpack_tar_gz(archive: &[u8], dest: &Path) {
+    let decoder = flate2::read::GzDecoder::new(archive);
+    let mut archive = tar::Archive::new(decoder);
+    archive
+        .unpack(dest)
+        .expect("failed to unpack toolchain archive");
+}
+
+/// Ensure a toolchain matching `api_version` and `host_triple` is available,
+/// returning the path to its `arm-none-eabi/include` directory.
+///
+/// If `sdk_toolchain` (the toolchain bundled next to the SDK, e.g.
+/// `toolchain/x86_64-linux/arm-none-eabi/include`) already exists, it's used
+/// as-is. Otherwise the matching release is downloaded for `host_triple`
+/// into a per-version, per-host cache directory under
+/// `$XDG_CACHE_HOME/flipperzero-toolchain`, unless `offline` is set, in
+/// which case we fail fast instead.
+pub fn ensure_toolchain(sdk_toolchain: &Path, api_version: u32, host_triple: &str, offline: bool) -> PathBuf {
+    if sdk_toolchain.is_dir() {
+        return sdk_toolchain.to_path_buf();
+    }
+
+    if offline {
+        panic!(
+            concat!(
+                "Failed to find toolchain at {:?}.\n",
+                "Running with --offline, so it will not be downloaded.\n",
+                "You may need to download it first."
+            ),
+            sdk_toolchain
+        )
+    }
+
+    let release = select_release(api_version);
+    let version_dir = cache_dir(host_triple).join(format!("{:08X}", release.api_version));
+    let include_dir = version_dir.join("arm-none-eabi/include");
+
+    if include_dir.is_dir() {
+        return include_dir;
+    }
+
+    fs::create_dir_all(&version_dir).expect("failed to create toolchain cache directory");
+
+    let url = release.url_template.replace("{host}", host_triple);
+    let archive = download_and_verify(&url);
+    unpack_tar_gz(&archive, &version_dir);
+
+    if !include_dir.is_dir() {
+        panic!(
+            "unpacked toolchain at {:?} is missing expected directory {:?}",
+            version_dir, include_dir
+        );
+    }
+
+    include_dir
+}
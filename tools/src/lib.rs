@@ -0,0 +1,4 @@
+//! Shared support code for the `flipperzero` SDK build tools.
+
+pub mod native_build;
+pub mod sdk_opts;
@@ -0,0 +1,210 @@
+//! Compile and link bundled `.c`/`.cpp`/`.s` sources against the SDK.
+//!
+//! Wraps [`cc::Build`] so a project's native shims are always compiled with
+//! the same include paths, defines, and Cortex-M4 target flags as the SDK's
+//! own [`SdkOpts`], rather than a hand-copied subset of them.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::sdk_opts::SdkOpts;
+
+/// Rust target triple for the Flipper Zero's Cortex-M4F.
+const TARGET_TRIPLE: &str = "thumbv7em-none-eabihf";
+
+/// Builder for a static archive of bundled native sources.
+///
+/// Seed it with [`NativeBuild::new`], add sources and any extra per-file
+/// include dirs/defines, then call [`NativeBuild::compile`].
+///
+/// Unlike a `build.rs`-driven [`cc::Build`], this runs from a standalone CLI
+/// binary, so none of `TARGET`/`HOST`/`OPT_LEVEL`/`OUT_DIR` are set by
+/// cargo. `NativeBuild` derives/supplies all four itself via `cc::Build`'s
+/// own setters rather than relying on that environment.
+pub struct NativeBuild {
+    toolchain_bin: PathBuf,
+    out_dir: PathBuf,
+    cc_flags: Vec<String>,
+    cpp_flags: Vec<String>,
+    linker_args: Vec<String>,
+    linker_script: Option<String>,
+    files: Vec<PathBuf>,
+    has_cpp: bool,
+    extra_flags: Vec<String>,
+    extra_includes: Vec<PathBuf>,
+    extra_defines: Vec<(String, Option<String>)>,
+}
+
+impl NativeBuild {
+    /// Start a native build seeded with the SDK's `cc_args`/`cpp_args`,
+    /// `linker_args`, and `linker_script` from `sdk_opts`.
+    ///
+    /// `toolchain_bin` is the `bin` directory of the `arm-none-eabi`
+    /// toolchain (next to the `arm-none-eabi` directory whose `include` path
+    /// `generate-bindings`' `toolchain::ensure_toolchain` returns), used to pick the cross compiler and archiver instead of the host's.
+    /// `out_dir` is where object files and the final archive are written;
+    /// the caller is responsible for it existing.
+    pub fn new(sdk_opts: &SdkOpts, sdk_root: &Path, toolchain_bin: &Path, out_dir: &Path) -> Self {
+        let replace_sdk_root_dir = |s: &str| {
+            s.replace("SDK_ROOT_DIR", sdk_root.to_str().unwrap()).replace('\\', "/")
+        };
+
+        let cc_flags = shlex::split(&replace_sdk_root_dir(&sdk_opts.cc_args))
+            .expect("failed to split sdk.opts cc_args");
+        let cpp_flags = shlex::split(&replace_sdk_root_dir(&sdk_opts.cpp_args))
+            .expect("failed to split sdk.opts cpp_args");
+
+        let linker_args = shlex::split(&replace_sdk_root_dir(&sdk_opts.linker_args))
+            .expect("failed to split sdk.opts linker_args");
+
+        let linker_script = (!sdk_opts.linker_script.is_empty())
+            .then(|| replace_sdk_root_dir(&sdk_opts.linker_script));
+
+        NativeBuild {
+            toolchain_bin: toolchain_bin.to_path_buf(),
+            out_dir: out_dir.to_path_buf(),
+            cc_flags,
+            cpp_flags,
+            linker_args,
+            linker_script,
+            files: Vec::new(),
+            has_cpp: false,
+            extra_flags: Vec::new(),
+            extra_includes: Vec::new(),
+            extra_defines: Vec::new(),
+        }
+    }
+
+    /// Add a `.c`, `.cpp`, or `.s` source file to the build.
+    ///
+    /// Whether `cpp_args` (rather than just `cc_args`) applies, and whether
+    /// `arm-none-eabi-g++` (rather than `-gcc`) does the compiling, is
+    /// decided at [`NativeBuild::compile`] time by whether any added file
+    /// looks like C++ (`.cpp`/`.cxx`/`.cc`/`.C`).
+    pub fn file<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        let path = path.as_ref();
+        if is_cpp_source(path) {
+            self.has_cpp = true;
+        }
+        self.files.push(path.to_path_buf());
+        self
+    }
+
+    /// Add several source files at once.
+    pub fn files<P, I>(&mut self, paths: I) -> &mut Self
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = P>,
+    {
+        for path in paths {
+            self.file(path);
+        }
+        self
+    }
+
+    /// Add a raw compiler flag, in addition to the SDK's own `cc_args`/`cpp_args`.
+    pub fn flag(&mut self, flag: &str) -> &mut Self {
+        self.extra_flags.push(flag.to_string());
+        self
+    }
+
+    /// Add a per-file (well, per-build) include directory, in addition to
+    /// the ones baked into the SDK's `cc_args`.
+    pub fn include<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.extra_includes.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Define a preprocessor macro, in addition to the SDK's own defines.
+    pub fn define<V: AsRef<OsStr>>(&mut self, name: &str, value: Option<V>) -> &mut Self {
+        self.extra_defines.push((
+            name.to_string(),
+            value.map(|v| v.as_ref().to_str().expect("define value is not valid UTF-8").to_string()),
+        ));
+        self
+    }
+
+    /// Compile the archive and emit the `cargo:rustc-link-*` directives
+    /// needed to link it (and the SDK's own linker flags) into the final
+    /// binary.
+    ///
+    /// `output` is the library name without the `lib`/`.a` decoration,
+    /// matching `cc::Build::compile`.
+    pub fn compile(&mut self, output: &str) {
+        let compiler = if self.has_cpp { "arm-none-eabi-g++" } else { "arm-none-eabi-gcc" };
+
+        let mut build = cc::Build::new();
+        build
+            .cpp(self.has_cpp)
+            .compiler(self.toolchain_bin.join(compiler))
+            .archiver(self.toolchain_bin.join("arm-none-eabi-ar"))
+            .target(TARGET_TRIPLE)
+            .host(&rustc_host_triple())
+            .opt_level(2)
+            .out_dir(&self.out_dir)
+            // Many files, independent translation units: let `cc` fan out
+            // across `NUM_JOBS` the same way cargo itself does.
+            .parallel(true);
+
+        for flag in &self.cc_flags {
+            build.flag(flag);
+        }
+        if self.has_cpp {
+            for flag in &self.cpp_flags {
+                build.flag(flag);
+            }
+        }
+
+        // User overrides always land after the SDK's own flags.
+        for dir in &self.extra_includes {
+            build.include(dir);
+        }
+        for (name, value) in &self.extra_defines {
+            build.define(name, value.as_deref());
+        }
+        for flag in &self.extra_flags {
+            build.flag(flag);
+        }
+
+        build.files(&self.files);
+        build.compile(output);
+
+        println!("cargo:rustc-link-search=native={}", self.out_dir.display());
+        println!("cargo:rustc-link-lib=static={}", output);
+
+        for arg in &self.linker_args {
+            println!("cargo:rustc-link-arg={}", arg);
+        }
+
+        if let Some(script) = &self.linker_script {
+            println!("cargo:rustc-link-arg=-T{}", script);
+        }
+    }
+}
+
+/// Whether `path` looks like a C++ (rather than C or assembly) source file.
+fn is_cpp_source(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(OsStr::to_str),
+        Some("cpp") | Some("cxx") | Some("cc") | Some("C")
+    )
+}
+
+/// Best-effort rustc-style host triple, for `cc::Build::host`.
+///
+/// Outside a `build.rs`, `cc` can't read `HOST` from the environment, so we
+/// supply one directly; it only needs to be accurate enough for `cc` to tell
+/// that the Cortex-M4F target isn't the host (i.e. that it's cross-compiling).
+fn rustc_host_triple() -> String {
+    let arch = std::env::consts::ARCH;
+    match std::env::consts::OS {
+        "linux" => format!("{}-unknown-linux-gnu", arch),
+        "macos" => format!("{}-apple-darwin", arch),
+        "windows" => format!("{}-pc-windows-msvc", arch),
+        os => panic!(
+            "unable to determine a host triple for OS {:?} / arch {:?}; \
+             native source compilation isn't supported on this host",
+            os, arch
+        ),
+    }
+}
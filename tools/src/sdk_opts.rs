@@ -0,0 +1,31 @@
+//! Shared `sdk.opts` model.
+//!
+//! `sdk.opts` is the SDK's own record of the flags it was built with. Both
+//! `generate-bindings` (to drive bindgen) and [`crate::native_build`] (to
+//! compile bundled native sources) start from this same struct so the two
+//! never drift apart on include paths, defines, or the linker script.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct SdkOpts {
+    pub sdk_symbols: String,
+    pub cc_args: String,
+    pub cpp_args: String,
+    pub linker_args: String,
+    pub linker_script: String,
+}
+
+/// Load `sdk.opts` file of compiler flags.
+pub fn load_sdk_opts<T: AsRef<Path>>(path: T) -> SdkOpts {
+    let file = fs::File::open(path.as_ref())
+        .expect("failed to open sdk.opts");
+
+    let sdk_opts: SdkOpts = serde_json::from_reader(file)
+        .expect("failed to parse sdk.opts JSON");
+
+    sdk_opts
+}